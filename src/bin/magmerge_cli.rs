@@ -2,6 +2,7 @@ use std::env;
 use std::io::{self, Write};
 
 fn main() {
+    env_logger::init();
     let args: Vec<String> = env::args().collect();
     let mut stdout = io::stdout();
     let mut stderr = io::stderr();
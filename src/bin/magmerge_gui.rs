@@ -1,17 +1,22 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
 
 use eframe::egui;
 use magmerge::{
-    collect_errors, collect_warnings, combine_folder_with_progress, format_group_output,
-    CombineReport, ProgressEvent,
+    collect_errors, collect_warnings, combine_folder_with_options, format_group_output,
+    watch_folder, CombineOptions, CombineReport, ProgressEvent,
 };
 
 fn main() -> eframe::Result<()> {
+    env_logger::init();
     let mut options = eframe::NativeOptions::default();
     if let Some(icon) = load_app_icon() {
         options.viewport = options.viewport.with_icon(Arc::new(icon));
@@ -45,8 +50,28 @@ struct CombinerApp {
     scanning: bool,
     progress_rx: Option<mpsc::Receiver<ProgressEvent>>,
     result_rx: Option<mpsc::Receiver<CombineReport>>,
+    watching: bool,
+    watch_rx: Option<mpsc::Receiver<CombineReport>>,
+    stop_flag: Option<Arc<AtomicBool>>,
+    preview_rx: Option<mpsc::Receiver<Vec<FilePreview>>>,
+    previews: Vec<FilePreview>,
+    show_tail: bool,
 }
 
+/// A bounded preview of one generated combined file: the first and last
+/// [`PREVIEW_LINES`] lines plus the full line/byte totals, read on the
+/// background thread so the UI stays responsive on large outputs.
+struct FilePreview {
+    path: PathBuf,
+    head: Vec<String>,
+    tail: Vec<String>,
+    total_lines: usize,
+    total_bytes: u64,
+}
+
+/// How many head/tail lines the preview pane keeps in memory per file.
+const PREVIEW_LINES: usize = 20;
+
 impl Default for CombinerApp {
     fn default() -> Self {
         Self {
@@ -62,8 +87,46 @@ impl Default for CombinerApp {
             scanning: false,
             progress_rx: None,
             result_rx: None,
+            watching: false,
+            watch_rx: None,
+            stop_flag: None,
+            preview_rx: None,
+            previews: Vec::new(),
+            show_tail: false,
+        }
+    }
+}
+
+/// Read a bounded preview of `path`: the first and last [`PREVIEW_LINES`] lines
+/// plus the exact line and byte totals, without holding the whole file in
+/// memory.
+fn read_preview(path: &Path) -> std::io::Result<FilePreview> {
+    let file = fs::File::open(path)?;
+    let total_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let reader = BufReader::new(file);
+
+    let mut head = Vec::new();
+    let mut tail: VecDeque<String> = VecDeque::with_capacity(PREVIEW_LINES);
+    let mut total_lines = 0usize;
+    for line in reader.lines() {
+        let line = line?;
+        total_lines += 1;
+        if head.len() < PREVIEW_LINES {
+            head.push(line.clone());
+        }
+        if tail.len() == PREVIEW_LINES {
+            tail.pop_front();
         }
+        tail.push_back(line);
     }
+
+    Ok(FilePreview {
+        path: path.to_path_buf(),
+        head,
+        tail: tail.into_iter().collect(),
+        total_lines,
+        total_bytes,
+    })
 }
 
 impl eframe::App for CombinerApp {
@@ -90,14 +153,22 @@ impl eframe::App for CombinerApp {
                     self.discovered_bead = 0;
                     self.discovered_motor = 0;
                     self.scanning = true;
+                    self.previews.clear();
+                    self.preview_rx = None;
 
                     let (progress_tx, progress_rx) = mpsc::channel();
                     let (result_tx, result_rx) = mpsc::channel();
                     self.progress_rx = Some(progress_rx);
                     self.result_rx = Some(result_rx);
 
+                    let stop = Arc::new(AtomicBool::new(false));
+                    self.stop_flag = Some(Arc::clone(&stop));
+                    let options = CombineOptions {
+                        stop: Some(stop),
+                        ..CombineOptions::default()
+                    };
                     thread::spawn(move || {
-                        let report = combine_folder_with_progress(&folder, |update| {
+                        let report = combine_folder_with_options(&folder, &options, |update| {
                             let _ = progress_tx.send(update);
                         });
                         let _ = result_tx.send(report);
@@ -154,23 +225,85 @@ impl eframe::App for CombinerApp {
                 self.processing = false;
                 self.progress_rx = None;
                 self.result_rx = None;
+                self.stop_flag = None;
                 self.current_file = None;
                 self.scanning = false;
-                if report.bead_files == 0 && report.motor_files == 0 {
+                if report.cancelled {
+                    self.status_message = Some("Combine cancelled (partial output).".to_string());
+                } else if report.bead_files == 0 && report.motor_files == 0 {
                     self.status_message = Some("No matching files found.".to_string());
                 } else {
                     self.status_message = Some("Combine complete.".to_string());
                 }
+
+                // Read previews of the generated files off-thread so large
+                // outputs don't block the UI.
+                let outputs: Vec<PathBuf> = report
+                    .groups
+                    .iter()
+                    .filter_map(|group| group.output_path.clone())
+                    .collect();
+                if !outputs.is_empty() {
+                    let (preview_tx, preview_rx) = mpsc::channel();
+                    self.preview_rx = Some(preview_rx);
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        let previews = outputs
+                            .iter()
+                            .filter_map(|path| read_preview(path).ok())
+                            .collect();
+                        let _ = preview_tx.send(previews);
+                        ctx.request_repaint();
+                    });
+                }
+
                 self.report = Some(report);
             }
         }
 
+        if let Some(rx) = &self.watch_rx {
+            while let Ok(report) = rx.try_recv() {
+                if report.bead_files == 0 && report.motor_files == 0 {
+                    self.status_message = Some("Watching: no matching files found.".to_string());
+                } else {
+                    self.status_message = Some("Watching: re-combined on change.".to_string());
+                }
+                self.report = Some(report);
+            }
+        }
+
+        if let Some(rx) = &self.preview_rx {
+            if let Ok(previews) = rx.try_recv() {
+                self.previews = previews;
+                self.preview_rx = None;
+            }
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("MagMerge");
             ui.label("Drop a folder here to combine Bead and Motor files.");
 
             if let Some(folder) = &self.folder {
                 ui.label(format!("Folder: {}", folder.display()));
+
+                if !self.watching && !self.processing {
+                    if ui.button("Watch folder for changes").clicked() {
+                        let folder = folder.clone();
+                        let (watch_tx, watch_rx) = mpsc::channel();
+                        self.watch_rx = Some(watch_rx);
+                        self.watching = true;
+                        let ctx = ctx.clone();
+                        let options = CombineOptions::default();
+                        thread::spawn(move || {
+                            let _ = watch_folder(&folder, &options, None, |report| {
+                                let _ = watch_tx.send(report);
+                                ctx.request_repaint();
+                            });
+                        });
+                    }
+                } else if self.watching {
+                    ui.label("Watching for new position files...");
+                }
             }
 
             if let Some(message) = &self.status_message {
@@ -195,6 +328,12 @@ impl eframe::App for CombinerApp {
                 if let Some(current) = &self.current_file {
                     ui.label(format!("Processing: {}", current));
                 }
+                if let Some(stop) = &self.stop_flag {
+                    if ui.button("Stop").clicked() {
+                        stop.store(true, Ordering::Relaxed);
+                        self.status_message = Some("Stopping...".to_string());
+                    }
+                }
             }
 
             if let Some(report) = &self.report {
@@ -249,6 +388,53 @@ impl eframe::App for CombinerApp {
                             }
                         });
                 }
+
+                if !report.timings.is_empty() {
+                    ui.separator();
+                    ui.label("Timings:");
+                    for (stage, duration) in &report.timings {
+                        ui.label(format!("- {stage}: {:.3?}", duration));
+                    }
+                }
+            }
+
+            if !self.previews.is_empty() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Preview:");
+                    ui.checkbox(&mut self.show_tail, "Show tail");
+                });
+                let show_tail = self.show_tail;
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for preview in &self.previews {
+                            let name = preview
+                                .path
+                                .file_name()
+                                .map(|n| n.to_string_lossy().to_string())
+                                .unwrap_or_else(|| preview.path.display().to_string());
+                            ui.strong(format!(
+                                "{name} ({} lines, {} bytes)",
+                                preview.total_lines, preview.total_bytes
+                            ));
+
+                            let lines = if show_tail {
+                                &preview.tail
+                            } else {
+                                &preview.head
+                            };
+                            for line in lines {
+                                ui.monospace(line);
+                            }
+                            if preview.total_lines > lines.len() {
+                                ui.weak(format!(
+                                    "... ({} more lines)",
+                                    preview.total_lines - lines.len()
+                                ));
+                            }
+                        }
+                    });
             }
         });
 
@@ -1,26 +1,213 @@
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-use crate::{collect_errors, collect_warnings, combine_folder, CombineReport, GroupSummary};
+use crate::{
+    collect_errors, collect_warnings, combine_folder_with_config, combine_folder_with_options,
+    watch_folder, ClassifyConfig, CombineOptions, CombineReport, GroupSummary, MergeMode,
+};
+
+const USAGE: &str = "Usage: magmerge_cli [--watch] [--dry-run] [--backup] [--json] \
+     [--json-compact] [--format text|json] [--recursive] [--max-depth <n>] \
+     [--follow-symlinks] [--threads <n>] [--chronological] [--config <file.toml>] \
+     [--exclude <pattern>] <folder>";
 
 pub fn run_cli(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> i32 {
-    if args.len() != 2 {
-        let _ = writeln!(err, "Usage: magmerge_cli <folder>");
-        return 2;
+    let mut watch = false;
+    let mut json = false;
+    let mut json_compact = false;
+    let mut options = CombineOptions::default();
+    let mut config_path: Option<PathBuf> = None;
+    let mut excludes: Vec<String> = Vec::new();
+    let mut folder_arg: Option<&String> = None;
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--watch" => watch = true,
+            "--dry-run" => options.dry_run = true,
+            "--backup" => options.backup = true,
+            "--recursive" => options.recursive = true,
+            "--follow-symlinks" => options.follow_symlinks = true,
+            "--chronological" => options.mode = MergeMode::Chronological,
+            "--threads" => match iter.next() {
+                Some(value) => match value.parse::<usize>() {
+                    Ok(threads) => options.threads = Some(threads),
+                    Err(_) => {
+                        let _ = writeln!(err, "Error: --threads expects a number");
+                        return 2;
+                    }
+                },
+                None => {
+                    let _ = writeln!(err, "Error: --threads requires a value");
+                    return 2;
+                }
+            },
+            "--max-depth" => match iter.next() {
+                Some(value) => match value.parse::<usize>() {
+                    Ok(depth) => {
+                        options.recursive = true;
+                        options.max_depth = Some(depth);
+                    }
+                    Err(_) => {
+                        let _ = writeln!(err, "Error: --max-depth expects a number");
+                        return 2;
+                    }
+                },
+                None => {
+                    let _ = writeln!(err, "Error: --max-depth requires a value");
+                    return 2;
+                }
+            },
+            "--json" => json = true,
+            "--json-compact" => {
+                json = true;
+                json_compact = true;
+            }
+            "--format" => match iter.next().map(String::as_str) {
+                Some("json") => json = true,
+                Some("text") => json = false,
+                Some(other) => {
+                    let _ = writeln!(err, "Error: unknown format: {other} (expected text or json)");
+                    return 2;
+                }
+                None => {
+                    let _ = writeln!(err, "Error: --format requires a value (text or json)");
+                    return 2;
+                }
+            },
+            "--config" => match iter.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => {
+                    let _ = writeln!(err, "Error: --config requires a file path");
+                    return 2;
+                }
+            },
+            "--exclude" => match iter.next() {
+                Some(pattern) => excludes.push(pattern.clone()),
+                None => {
+                    let _ = writeln!(err, "Error: --exclude requires a pattern");
+                    return 2;
+                }
+            },
+            other if other.starts_with("--") => {
+                let _ = writeln!(err, "Error: unknown option: {other}");
+                return 2;
+            }
+            _ => {
+                if folder_arg.is_some() {
+                    let _ = writeln!(err, "{USAGE}");
+                    return 2;
+                }
+                folder_arg = Some(arg);
+            }
+        }
     }
 
-    let folder = PathBuf::from(&args[1]);
+    let folder = match folder_arg {
+        Some(folder) => PathBuf::from(folder),
+        None => {
+            let _ = writeln!(err, "{USAGE}");
+            return 2;
+        }
+    };
     if !folder.is_dir() {
         let _ = writeln!(err, "Error: not a folder: {}", folder.display());
         return 2;
     }
 
-    let report = combine_folder(&folder);
-    print_report(&report, out);
+    // An explicit --config wins; otherwise fall back to a `magmerge.toml` in the
+    // target folder, and finally to the built-in Bead/Motor pair.
+    let mut config = match &config_path {
+        Some(path) => match ClassifyConfig::load(path) {
+            Ok(config) => config,
+            Err(err_) => {
+                let _ = writeln!(err, "Error: failed to load config {}: {err_}", path.display());
+                return 2;
+            }
+        },
+        None => match ClassifyConfig::load_from_folder(&folder) {
+            Ok(config) => config,
+            Err(err_) => {
+                let _ = writeln!(err, "Error: failed to load folder config: {err_}");
+                return 2;
+            }
+        },
+    };
+    let folder_has_config = config_path.is_none() && folder.join("magmerge.toml").is_file();
+    let use_config = config_path.is_some() || folder_has_config || !excludes.is_empty();
+    config.excludes.extend(excludes);
+
+    // Trap Ctrl-C so a long combine over a huge folder can be aborted cleanly,
+    // leaving a partial output rather than a half-written file. Watch mode
+    // leaves Ctrl-C alone so that it still ends the process.
+    let stop = Arc::new(AtomicBool::new(false));
+    if !watch {
+        let stop = Arc::clone(&stop);
+        let _ = ctrlc::set_handler(move || stop.store(true, Ordering::Relaxed));
+    }
+    options.stop = Some(Arc::clone(&stop));
+
+    if watch {
+        let _ = writeln!(out, "Watching {} (Ctrl-C to stop)...", folder.display());
+        let watch_config = if use_config { Some(&config) } else { None };
+        let result = watch_folder(&folder, &options, watch_config, |report| {
+            if json {
+                print_json(&report, json_compact, out);
+            } else if use_config {
+                print_config_report(&report, options.dry_run, out);
+                let _ = writeln!(out, "---");
+            } else {
+                print_report(&report, options.dry_run, out);
+                let _ = writeln!(out, "---");
+            }
+        });
+        if let Err(err_) = result {
+            let _ = writeln!(err, "Error: watch failed: {err_}");
+            return 1;
+        }
+        return 0;
+    }
+
+    let report = if use_config {
+        combine_folder_with_config(&folder, &config, &options, |_| {})
+    } else {
+        combine_folder_with_options(&folder, &options, |_| {})
+    };
+    if json {
+        print_json(&report, json_compact, out);
+    } else if use_config {
+        print_config_report(&report, options.dry_run, out);
+    } else {
+        print_report(&report, options.dry_run, out);
+    }
     0
 }
 
-fn print_report(report: &CombineReport, out: &mut dyn Write) {
+fn print_json(report: &CombineReport, compact: bool, out: &mut dyn Write) {
+    let serialized = if compact {
+        serde_json::to_string(report)
+    } else {
+        serde_json::to_string_pretty(report)
+    };
+    match serialized {
+        Ok(json) => {
+            let _ = writeln!(out, "{json}");
+        }
+        Err(err) => {
+            let _ = writeln!(out, "{{\"error\":\"failed to serialize report: {err}\"}}");
+        }
+    }
+}
+
+fn print_report(report: &CombineReport, dry_run: bool, out: &mut dyn Write) {
+    if dry_run {
+        let _ = writeln!(out, "Dry run (no files written).");
+    }
+    if report.cancelled {
+        let _ = writeln!(out, "Cancelled (partial output).");
+    }
     let _ = writeln!(out, "Folder: {}", report.folder.display());
     let _ = writeln!(out, "Bead files: {}", report.bead_files);
     let _ = writeln!(out, "Motor files: {}", report.motor_files);
@@ -31,13 +218,13 @@ fn print_report(report: &CombineReport, out: &mut dyn Write) {
     }
 
     if let Some(summary) = report.bead.as_ref() {
-        print_group(summary, "Bead", out);
+        print_group(summary, "Bead", dry_run, out);
     } else {
         let _ = writeln!(out, "Bead output: (not created)");
     }
 
     if let Some(summary) = report.motor.as_ref() {
-        print_group(summary, "Motor", out);
+        print_group(summary, "Motor", dry_run, out);
     } else {
         let _ = writeln!(out, "Motor output: (not created)");
     }
@@ -61,18 +248,78 @@ fn print_report(report: &CombineReport, out: &mut dyn Write) {
             }
         }
     }
+
+    if !report.timings.is_empty() {
+        let _ = writeln!(out, "Timings:");
+        for (stage, duration) in &report.timings {
+            let _ = writeln!(out, "- {stage}: {:.3?}", duration);
+        }
+    }
+}
+
+fn print_config_report(report: &CombineReport, dry_run: bool, out: &mut dyn Write) {
+    if dry_run {
+        let _ = writeln!(out, "Dry run (no files written).");
+    }
+    if report.cancelled {
+        let _ = writeln!(out, "Cancelled (partial output).");
+    }
+    let _ = writeln!(out, "Folder: {}", report.folder.display());
+
+    if report.groups.is_empty() {
+        let _ = writeln!(out, "No matching files found.");
+    } else {
+        for summary in &report.groups {
+            let _ = writeln!(out, "{} files: {}", summary.group, summary.input_files);
+            let label = summary.group.clone();
+            print_group(summary, &label, dry_run, out);
+        }
+    }
+
+    let warnings: Vec<_> = report.groups.iter().flat_map(|g| g.warnings.iter()).collect();
+    if !warnings.is_empty() {
+        let _ = writeln!(out, "Warnings:");
+        for warning in warnings {
+            let _ = writeln!(out, "- {}: {}", warning.file.display(), warning.message);
+        }
+    }
+
+    let mut errors: Vec<&crate::Error> = report.errors.iter().collect();
+    errors.extend(report.groups.iter().flat_map(|g| g.errors.iter()));
+    if !errors.is_empty() {
+        let _ = writeln!(out, "Errors:");
+        for error in errors {
+            if let Some(file) = error.file.as_ref() {
+                let _ = writeln!(out, "- {}: {}", file.display(), error.message);
+            } else {
+                let _ = writeln!(out, "- {}", error.message);
+            }
+        }
+    }
+
+    if !report.timings.is_empty() {
+        let _ = writeln!(out, "Timings:");
+        for (stage, duration) in &report.timings {
+            let _ = writeln!(out, "- {stage}: {:.3?}", duration);
+        }
+    }
 }
 
-fn print_group(summary: &GroupSummary, label: &str, out: &mut dyn Write) {
+fn print_group(summary: &GroupSummary, label: &str, dry_run: bool, out: &mut dyn Write) {
     let output = summary
         .output_path
         .as_ref()
         .map(|p| p.display().to_string())
         .unwrap_or_else(|| "(not created)".to_string());
 
+    let verb = if dry_run { "would write" } else { "output" };
     let _ = writeln!(
         out,
-        "{} output: {} (lines: {})",
-        label, output, summary.data_lines
+        "{label} {verb}: {output} (lines: {})",
+        summary.data_lines
     );
+
+    if let Some(backup) = summary.backup_path.as_ref() {
+        let _ = writeln!(out, "{label} backed up previous output to: {}", backup.display());
+    }
 }
@@ -1,7 +1,18 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
 use std::fs;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize, Serializer};
 
 pub mod cli;
 
@@ -9,6 +20,9 @@ const BEAD_PREFIX: &str = "Bead Positions";
 const MOTOR_PREFIX: &str = "Motor Positions";
 const BEAD_OUTPUT: &str = "Bead Positions Combined.txt";
 const MOTOR_OUTPUT: &str = "Motor Positions Combined.txt";
+/// Optional per-folder classification config, auto-loaded by
+/// [`ClassifyConfig::load_from_folder`].
+const CONFIG_FILE_NAME: &str = "magmerge.toml";
 
 #[derive(Debug, Clone)]
 pub enum ProgressEvent {
@@ -24,49 +38,274 @@ pub enum ProgressEvent {
     },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum FileType {
     Bead,
     Motor,
 }
 
+/// How the data lines of a group's files are ordered in the combined output.
+///
+/// `Concatenate` keeps the original behaviour: data lines are emitted in
+/// filename order. `Chronological` parses the leading column of each line as a
+/// timestamp and performs a stable k-way merge across files, suppressing exact
+/// duplicate rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeMode {
+    #[default]
+    Concatenate,
+    Chronological,
+}
+
 #[derive(Debug, Clone)]
 pub struct DiscoveredFiles {
     pub bead_files: Vec<PathBuf>,
     pub motor_files: Vec<PathBuf>,
 }
 
+/// Opt-in tuning for a folder combine.
+///
+/// The default scans only the target folder with a single `read_dir` and reads
+/// its files on rayon's global pool. Set `recursive` to descend into
+/// nested session folders; `max_depth` bounds how far the walk goes (`None`
+/// means unlimited, `Some(0)` is the target folder only).
 #[derive(Debug, Clone)]
+pub struct CombineOptions {
+    pub recursive: bool,
+    pub max_depth: Option<usize>,
+    /// Run the full discovery/parse path and populate the report, but never
+    /// touch the filesystem, so callers can preview what would be produced.
+    pub dry_run: bool,
+    /// Rename an existing combined output to a timestamped sidecar before
+    /// overwriting it.
+    pub backup: bool,
+    /// When walking recursively, whether to descend into symlinked directories.
+    /// Off by default so a self-referential link can't loop the traversal.
+    pub follow_symlinks: bool,
+    /// Optional cooperative cancellation token. When set and flipped to `true`,
+    /// the combine loop stops before the next file and returns a partial,
+    /// [`CombineReport::cancelled`]-marked report.
+    pub stop: Option<Arc<AtomicBool>>,
+    /// Worker threads used to read/parse input files. `None` uses rayon's
+    /// global pool (available parallelism); `Some(n)` pins the group read to a
+    /// dedicated `n`-thread pool. Output ordering is deterministic regardless.
+    /// Only [`MergeMode::Concatenate`] reads in parallel;
+    /// [`MergeMode::Chronological`] reads files one after another on the
+    /// calling thread and ignores this setting.
+    pub threads: Option<usize>,
+    /// How each group's data lines are ordered in the combined output. Defaults
+    /// to [`MergeMode::Concatenate`]; [`MergeMode::Chronological`] k-way merges
+    /// by the leading timestamp column and drops duplicate rows.
+    pub mode: MergeMode,
+}
+
+impl CombineOptions {
+    /// Whether cancellation has been requested through [`CombineOptions::stop`].
+    fn is_cancelled(&self) -> bool {
+        self.stop
+            .as_ref()
+            .map(|flag| flag.load(Ordering::Relaxed))
+            .unwrap_or(false)
+    }
+}
+
+impl Default for CombineOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            max_depth: None,
+            dry_run: false,
+            backup: false,
+            follow_symlinks: false,
+            stop: None,
+            threads: None,
+            mode: MergeMode::Concatenate,
+        }
+    }
+}
+
+/// How a file name is matched to a group. A file joins the first group whose
+/// configured pattern matches; `prefix`, `suffix`, and `regex` are all optional
+/// and combined with AND when more than one is set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupDef {
+    pub label: String,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub suffix: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    /// Per-group extension allowlist (without the leading dot), compared
+    /// case-insensitively. Empty means "any extension"; a group can thus match
+    /// `*.txt` while skipping `*.bak`/`*.tmp`.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub output: String,
+}
+
+impl GroupDef {
+    /// Whether `ext` (the file extension without its dot, or `None` when the
+    /// file has none) is permitted by this group's allowlist.
+    fn allows_extension(&self, ext: Option<&str>) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        match ext {
+            Some(ext) => self
+                .extensions
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(ext)),
+            None => false,
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        if let Some(prefix) = &self.prefix {
+            if !name.starts_with(prefix) {
+                return false;
+            }
+        }
+        if let Some(suffix) = &self.suffix {
+            if !name.ends_with(suffix) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.regex {
+            match regex::Regex::new(pattern) {
+                Ok(re) => {
+                    if !re.is_match(name) {
+                        return false;
+                    }
+                }
+                Err(_) => return false,
+            }
+        }
+        // A group with no patterns matches nothing, so require at least one.
+        self.prefix.is_some() || self.suffix.is_some() || self.regex.is_some()
+    }
+}
+
+/// User-supplied classification rules: the set of groups to produce and a list
+/// of exclude globs applied during discovery. The [`Default`] is the built-in
+/// Bead/Motor pair, so callers that don't configure anything keep the original
+/// behaviour.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassifyConfig {
+    pub groups: Vec<GroupDef>,
+    #[serde(default)]
+    pub excludes: Vec<String>,
+}
+
+impl Default for ClassifyConfig {
+    fn default() -> Self {
+        Self {
+            groups: vec![
+                GroupDef {
+                    label: "Bead".to_string(),
+                    prefix: Some(BEAD_PREFIX.to_string()),
+                    suffix: None,
+                    regex: None,
+                    extensions: vec!["txt".to_string()],
+                    output: BEAD_OUTPUT.to_string(),
+                },
+                GroupDef {
+                    label: "Motor".to_string(),
+                    prefix: Some(MOTOR_PREFIX.to_string()),
+                    suffix: None,
+                    regex: None,
+                    extensions: vec!["txt".to_string()],
+                    output: MOTOR_OUTPUT.to_string(),
+                },
+            ],
+            excludes: Vec::new(),
+        }
+    }
+}
+
+impl ClassifyConfig {
+    /// Load a configuration from a TOML file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Load `magmerge.toml` from `folder` if it exists, otherwise fall back to
+    /// the built-in Bead/Motor pair. A parse error is surfaced so a malformed
+    /// config is never silently ignored.
+    pub fn load_from_folder(folder: &Path) -> io::Result<Self> {
+        let path = folder.join(CONFIG_FILE_NAME);
+        if path.is_file() {
+            Self::load(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Warning {
     pub file: PathBuf,
     pub message: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Error {
     pub file: Option<PathBuf>,
     pub message: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct GroupSummary {
+    /// Authoritative group key (e.g. "Bead", "Motor", or a configured label).
+    pub group: String,
+    /// Internal Bead/Motor tag used for progress labelling only. Kept off the
+    /// JSON report because it collapses arbitrary configured labels onto
+    /// Bead/Motor and would mislead scripting consumers — `group` is the key.
+    #[serde(skip)]
     pub file_type: FileType,
     pub input_files: usize,
     pub output_path: Option<PathBuf>,
     pub data_lines: usize,
+    pub duplicate_lines: usize,
+    #[serde(serialize_with = "serialize_header")]
     pub header: Option<Vec<u8>>,
+    /// Path the previous output was moved to when `backup` was requested.
+    pub backup_path: Option<PathBuf>,
     pub warnings: Vec<Warning>,
     pub errors: Vec<Error>,
 }
 
-#[derive(Debug, Clone)]
+/// Serialize a header as lossy UTF-8 so JSON consumers get a readable string
+/// rather than a byte array.
+fn serialize_header<S>(header: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match header {
+        Some(bytes) => serializer.serialize_some(&String::from_utf8_lossy(bytes)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CombineReport {
     pub folder: PathBuf,
     pub bead_files: usize,
     pub motor_files: usize,
     pub bead: Option<GroupSummary>,
     pub motor: Option<GroupSummary>,
+    /// Every combined group, in configured order. For the default Bead/Motor
+    /// config this mirrors `bead`/`motor`; configured runs may hold N groups.
+    pub groups: Vec<GroupSummary>,
+    /// Wall-clock duration of each stage, e.g. ("discovery", ..),
+    /// ("bead combine", ..), ("motor combine", ..).
+    pub timings: Vec<(String, Duration)>,
     pub errors: Vec<Error>,
+    /// Set when the combine was aborted early via [`CombineOptions::stop`]; the
+    /// report then reflects only the files processed before cancellation.
+    #[serde(default)]
+    pub cancelled: bool,
 }
 
 pub fn discover_files(folder: &Path) -> io::Result<DiscoveredFiles> {
@@ -97,7 +336,7 @@ where
 
         let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
-        if is_combined_output(&name) {
+        if is_combined_output(&name) || is_backup_sidecar(&name) {
             continue;
         }
 
@@ -123,6 +362,83 @@ where
     })
 }
 
+/// Discover matching files, optionally descending into subfolders.
+///
+/// With the default [`CombineOptions`] this behaves exactly like
+/// [`discover_files_with_progress`]. When `recursive` is set the folder is
+/// walked breadth-first up to `max_depth` and every matching file under the
+/// tree is grouped together before sorting.
+pub fn discover_files_with_options<F>(
+    folder: &Path,
+    options: &CombineOptions,
+    mut on_discovery: F,
+) -> io::Result<DiscoveredFiles>
+where
+    F: FnMut(usize, usize),
+{
+    if !options.recursive {
+        return discover_files_with_progress(folder, on_discovery);
+    }
+
+    let mut bead_files = Vec::new();
+    let mut motor_files = Vec::new();
+
+    // Breadth-first worklist of (directory, depth).
+    let mut pending: VecDeque<(PathBuf, usize)> = VecDeque::from([(folder.to_path_buf(), 0)]);
+    while let Some((dir, depth)) = pending.pop_front() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            let is_symlink = entry.file_type().map(|ft| ft.is_symlink()).unwrap_or(false);
+            if is_symlink && !options.follow_symlinks {
+                continue;
+            }
+
+            if path.is_dir() {
+                if options.max_depth.map_or(true, |max| depth < max) {
+                    pending.push_back((path, depth + 1));
+                }
+                continue;
+            }
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let ext = path.extension().and_then(|e| e.to_str());
+            if ext != Some("txt") {
+                continue;
+            }
+
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if is_combined_output(name) || is_backup_sidecar(name) {
+                continue;
+            }
+
+            match classify_name(name) {
+                Some(FileType::Bead) => {
+                    bead_files.push(path);
+                    on_discovery(bead_files.len(), motor_files.len());
+                }
+                Some(FileType::Motor) => {
+                    motor_files.push(path);
+                    on_discovery(bead_files.len(), motor_files.len());
+                }
+                None => {}
+            }
+        }
+    }
+
+    sort_paths(&mut bead_files);
+    sort_paths(&mut motor_files);
+
+    Ok(DiscoveredFiles {
+        bead_files,
+        motor_files,
+    })
+}
+
 pub fn combine_folder(folder: &Path) -> CombineReport {
     combine_folder_with_progress(folder, |_| {})
 }
@@ -137,9 +453,13 @@ where
         motor_files: 0,
         bead: None,
         motor: None,
+        groups: Vec::new(),
+        timings: Vec::new(),
         errors: Vec::new(),
+        cancelled: false,
     };
 
+    let discovery_start = Instant::now();
     let discovered = match discover_files_with_progress(folder, |bead_files, motor_files| {
         on_progress(ProgressEvent::Discovery {
             bead_files,
@@ -154,47 +474,829 @@ where
             });
             return report;
         }
-    };
+    };
+    report.timings.push(("discovery".to_string(), discovery_start.elapsed()));
+
+    report.bead_files = discovered.bead_files.len();
+    report.motor_files = discovered.motor_files.len();
+    debug!(
+        "discovered {} bead and {} motor files under {}",
+        report.bead_files,
+        report.motor_files,
+        folder.display()
+    );
+    let total_files = report.bead_files + report.motor_files;
+    let mut processed_files = 0usize;
+    let mut on_file_processed = |file_type: FileType, path: &PathBuf| {
+        processed_files += 1;
+        debug!("processed {:?} file {}", file_type, path.display());
+        on_progress(ProgressEvent::Combine {
+            processed_files,
+            total_files,
+            file_type,
+            current_file: path.clone(),
+        });
+    };
+
+    if !discovered.bead_files.is_empty() {
+        let output = folder.join(output_filename(FileType::Bead));
+        let start = Instant::now();
+        let summary = combine_group_with_progress(
+            FileType::Bead,
+            &discovered.bead_files,
+            &output,
+            &mut on_file_processed,
+        );
+        log_group_issues(&summary);
+        report.timings.push(("bead combine".to_string(), start.elapsed()));
+        report.bead = Some(summary);
+    }
+
+    if !discovered.motor_files.is_empty() {
+        let output = folder.join(output_filename(FileType::Motor));
+        let start = Instant::now();
+        let summary = combine_group_with_progress(
+            FileType::Motor,
+            &discovered.motor_files,
+            &output,
+            &mut on_file_processed,
+        );
+        log_group_issues(&summary);
+        report.timings.push(("motor combine".to_string(), start.elapsed()));
+        report.motor = Some(summary);
+    }
+
+    if let Some(ref summary) = report.bead {
+        report.groups.push(summary.clone());
+    }
+    if let Some(ref summary) = report.motor {
+        report.groups.push(summary.clone());
+    }
+
+    report
+}
+
+/// Combine a folder with explicit [`CombineOptions`].
+///
+/// This is the recursive/parallel entry point. Discovery honours
+/// `options.recursive`/`options.max_depth`. In [`MergeMode::Concatenate`] mode
+/// each group's input files are read and parsed across a rayon worker pool
+/// before being stitched into the output in the existing sorted order, so
+/// results stay byte-for-byte deterministic regardless of thread count;
+/// [`MergeMode::Chronological`] reads the files sequentially on the calling
+/// thread. The sequential [`combine_group`]
+/// path is left untouched for callers that want it.
+pub fn combine_folder_with_options<F>(
+    folder: &Path,
+    options: &CombineOptions,
+    mut on_progress: F,
+) -> CombineReport
+where
+    F: FnMut(ProgressEvent),
+{
+    let mut report = CombineReport {
+        folder: folder.to_path_buf(),
+        bead_files: 0,
+        motor_files: 0,
+        bead: None,
+        motor: None,
+        groups: Vec::new(),
+        timings: Vec::new(),
+        errors: Vec::new(),
+        cancelled: false,
+    };
+
+    let discovery_start = Instant::now();
+    let discovered = match discover_files_with_options(folder, options, |bead_files, motor_files| {
+        on_progress(ProgressEvent::Discovery {
+            bead_files,
+            motor_files,
+        });
+    }) {
+        Ok(files) => files,
+        Err(err) => {
+            report.errors.push(Error {
+                file: None,
+                message: format!("Failed to scan folder: {err}"),
+            });
+            return report;
+        }
+    };
+    report.timings.push(("discovery".to_string(), discovery_start.elapsed()));
+
+    report.bead_files = discovered.bead_files.len();
+    report.motor_files = discovered.motor_files.len();
+    debug!(
+        "discovered {} bead and {} motor files under {}",
+        report.bead_files,
+        report.motor_files,
+        folder.display()
+    );
+    let total_files = report.bead_files + report.motor_files;
+    let mut processed_files = 0usize;
+    let mut on_file_processed = |file_type: FileType, path: &PathBuf| {
+        processed_files += 1;
+        debug!("processed {:?} file {}", file_type, path.display());
+        on_progress(ProgressEvent::Combine {
+            processed_files,
+            total_files,
+            file_type,
+            current_file: path.clone(),
+        });
+    };
+
+    if !discovered.bead_files.is_empty() {
+        let output = folder.join(output_filename(FileType::Bead));
+        let start = Instant::now();
+        let summary = combine_group_parallel(
+            FileType::Bead,
+            &discovered.bead_files,
+            &output,
+            options,
+            &mut on_file_processed,
+        );
+        log_group_issues(&summary);
+        report.timings.push(("bead combine".to_string(), start.elapsed()));
+        report.bead = Some(summary);
+    }
+
+    if !discovered.motor_files.is_empty() {
+        let output = folder.join(output_filename(FileType::Motor));
+        let start = Instant::now();
+        let summary = combine_group_parallel(
+            FileType::Motor,
+            &discovered.motor_files,
+            &output,
+            options,
+            &mut on_file_processed,
+        );
+        log_group_issues(&summary);
+        report.timings.push(("motor combine".to_string(), start.elapsed()));
+        report.motor = Some(summary);
+    }
+
+    if let Some(ref summary) = report.bead {
+        report.groups.push(summary.clone());
+    }
+    if let Some(ref summary) = report.motor {
+        report.groups.push(summary.clone());
+    }
+
+    report.cancelled = options.is_cancelled();
+    report
+}
+
+/// Emit `warn!` for every warning and error a group accumulated, so verbose
+/// logs and the structured [`CombineReport`] stay consistent.
+fn log_group_issues(summary: &GroupSummary) {
+    for warning in &summary.warnings {
+        warn!("{}: {}", warning.file.display(), warning.message);
+    }
+    for error in &summary.errors {
+        match &error.file {
+            Some(file) => warn!("{}: {}", file.display(), error.message),
+            None => warn!("{}", error.message),
+        }
+    }
+}
+
+/// Display/progress label for a built-in file type.
+fn group_label(file_type: FileType) -> String {
+    match file_type {
+        FileType::Bead => "Bead".to_string(),
+        FileType::Motor => "Motor".to_string(),
+    }
+}
+
+/// Pick the [`FileType`] that best fits a configured group label, used only to
+/// tag progress events — the authoritative key is [`GroupSummary::group`].
+fn file_type_for_label(label: &str) -> FileType {
+    if label.to_lowercase().contains("motor") {
+        FileType::Motor
+    } else {
+        FileType::Bead
+    }
+}
+
+/// Map a configured label onto a legacy [`FileType`] for the `bead`/`motor`
+/// back-compat fields, matching the literal built-in labels only. Arbitrary
+/// labels like "Force" return `None` so they don't masquerade as Bead/Motor.
+fn back_compat_type(label: &str) -> Option<FileType> {
+    match label {
+        "Bead" => Some(FileType::Bead),
+        "Motor" => Some(FileType::Motor),
+        _ => None,
+    }
+}
+
+/// Discover matching files for each configured group.
+///
+/// Returns one `Vec<PathBuf>` per entry in `config.groups`, index-aligned with
+/// it. Any file matching an exclude glob (by file name) or named like one of
+/// the groups' outputs is skipped. Each file joins the first group whose
+/// extension allowlist ([`GroupDef::extensions`]) and [`GroupDef::matches`]
+/// both accept it. `on_discovery`
+/// receives the running total of matched files after each addition.
+pub fn discover_with_config<F>(
+    folder: &Path,
+    config: &ClassifyConfig,
+    options: &CombineOptions,
+    mut on_discovery: F,
+) -> io::Result<Vec<Vec<PathBuf>>>
+where
+    F: FnMut(usize),
+{
+    let excludes: Vec<glob::Pattern> = config
+        .excludes
+        .iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect();
+    let outputs: HashSet<&str> = config.groups.iter().map(|g| g.output.as_str()).collect();
+
+    let mut grouped: Vec<Vec<PathBuf>> = vec![Vec::new(); config.groups.len()];
+    let mut matched = 0usize;
+
+    // Breadth-first worklist of (directory, depth); a single level when not
+    // recursive, mirroring discover_files_with_options.
+    let mut pending: VecDeque<(PathBuf, usize)> = VecDeque::from([(folder.to_path_buf(), 0)]);
+    while let Some((dir, depth)) = pending.pop_front() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            let is_symlink = entry.file_type().map(|ft| ft.is_symlink()).unwrap_or(false);
+            if is_symlink && !options.follow_symlinks {
+                continue;
+            }
+
+            if path.is_dir() {
+                if options.recursive && options.max_depth.map_or(true, |max| depth < max) {
+                    pending.push_back((path, depth + 1));
+                }
+                continue;
+            }
+            if !path.is_file() {
+                continue;
+            }
+
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if outputs.contains(name) || is_backup_sidecar(name) {
+                continue;
+            }
+            if excludes.iter().any(|pattern| pattern.matches(name)) {
+                continue;
+            }
+
+            let ext = path.extension().and_then(|e| e.to_str());
+            if let Some(index) = config
+                .groups
+                .iter()
+                .position(|group| group.allows_extension(ext) && group.matches(name))
+            {
+                grouped[index].push(path);
+                matched += 1;
+                on_discovery(matched);
+            }
+        }
+    }
+
+    for files in &mut grouped {
+        sort_paths(files);
+    }
+
+    Ok(grouped)
+}
+
+/// Combine a folder using explicit classification rules.
+///
+/// Generalises [`combine_folder_with_options`] to an arbitrary set of groups.
+/// Every configured group that matched at least one file is combined in order
+/// and pushed onto [`CombineReport::groups`]; groups with no matching files are
+/// left out. `bead`/`motor` are also populated when groups with
+/// those labels are present so existing consumers keep working.
+pub fn combine_folder_with_config<F>(
+    folder: &Path,
+    config: &ClassifyConfig,
+    options: &CombineOptions,
+    mut on_progress: F,
+) -> CombineReport
+where
+    F: FnMut(ProgressEvent),
+{
+    let mut report = CombineReport {
+        folder: folder.to_path_buf(),
+        bead_files: 0,
+        motor_files: 0,
+        bead: None,
+        motor: None,
+        groups: Vec::new(),
+        timings: Vec::new(),
+        errors: Vec::new(),
+        cancelled: false,
+    };
+
+    let discovery_start = Instant::now();
+    let grouped = match discover_with_config(folder, config, options, |matched| {
+        // A configured run can hold arbitrary group labels, so report the total
+        // matched-so-far under the bead counter — the GUI's "found" indicator
+        // advances as directories are descended regardless of group names.
+        on_progress(ProgressEvent::Discovery {
+            bead_files: matched,
+            motor_files: 0,
+        });
+    }) {
+        Ok(grouped) => grouped,
+        Err(err) => {
+            report.errors.push(Error {
+                file: None,
+                message: format!("Failed to scan folder: {err}"),
+            });
+            return report;
+        }
+    };
+    report.timings.push(("discovery".to_string(), discovery_start.elapsed()));
+
+    let total_files: usize = grouped.iter().map(|files| files.len()).sum();
+    for (def, files) in config.groups.iter().zip(&grouped) {
+        match back_compat_type(&def.label) {
+            Some(FileType::Bead) => report.bead_files += files.len(),
+            Some(FileType::Motor) => report.motor_files += files.len(),
+            None => {}
+        }
+        debug!("configured group {} matched {} files", def.label, files.len());
+    }
+
+    let mut processed_files = 0usize;
+    for (def, files) in config.groups.iter().zip(&grouped) {
+        if files.is_empty() || options.is_cancelled() {
+            continue;
+        }
+        let output = folder.join(&def.output);
+        let file_type = file_type_for_label(&def.label);
+        let mut summary = GroupSummary {
+            group: def.label.clone(),
+            file_type,
+            input_files: files.len(),
+            output_path: None,
+            data_lines: 0,
+            duplicate_lines: 0,
+            backup_path: None,
+            header: None,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let start = Instant::now();
+        let mut on_file = |path: &PathBuf| {
+            processed_files += 1;
+            on_progress(ProgressEvent::Combine {
+                processed_files,
+                total_files,
+                file_type,
+                current_file: path.clone(),
+            });
+        };
+        match options.mode {
+            MergeMode::Concatenate => {
+                combine_group_into(files, &output, options, &mut summary, &mut on_file);
+            }
+            MergeMode::Chronological => {
+                combine_group_chronological_into(files, &output, options, &mut summary, &mut on_file);
+            }
+        }
+        log_group_issues(&summary);
+        report
+            .timings
+            .push((format!("{} combine", def.label), start.elapsed()));
+
+        match back_compat_type(&def.label) {
+            Some(FileType::Bead) if report.bead.is_none() => report.bead = Some(summary.clone()),
+            Some(FileType::Motor) if report.motor.is_none() => report.motor = Some(summary.clone()),
+            _ => {}
+        }
+        report.groups.push(summary);
+    }
+
+    report.cancelled = options.is_cancelled();
+    report
+}
+
+/// Watch `folder` and re-combine whenever new position files land.
+///
+/// Registers a recursive [`notify`] watcher, runs an initial combine, then
+/// re-combines on every settled burst of create/modify events. Each re-combine
+/// honours the supplied `options` (recursive/backup/dry-run/threads/mode) and,
+/// when `config` is `Some`, the configured group/exclude rules — so the flags
+/// parsed for a one-shot run behave identically under `--watch`. Bursts are
+/// coalesced within ~500ms of quiet, and events that only touch the
+/// combined-output files are ignored so the tool doesn't react to its own
+/// writes. `on_report` is invoked with a fresh [`CombineReport`] after each
+/// run. Blocks until the watcher channel closes.
+pub fn watch_folder<F>(
+    folder: &Path,
+    options: &CombineOptions,
+    config: Option<&ClassifyConfig>,
+    mut on_report: F,
+) -> notify::Result<()>
+where
+    F: FnMut(CombineReport),
+{
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let recombine = || match config {
+        Some(config) => combine_folder_with_config(folder, config, options, |_| {}),
+        None => combine_folder_with_options(folder, options, |_| {}),
+    };
+
+    // Files the tool writes itself, so their events don't re-trigger the watch:
+    // the active config's outputs (or the built-in pair) plus backup sidecars.
+    let outputs: HashSet<&str> = match config {
+        Some(config) => config.groups.iter().map(|g| g.output.as_str()).collect(),
+        None => [BEAD_OUTPUT, MOTOR_OUTPUT].into_iter().collect(),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })?;
+    watcher.watch(folder, RecursiveMode::Recursive)?;
+
+    // Emit the current state before waiting for the first change.
+    on_report(recombine());
+
+    loop {
+        let first = match rx.recv() {
+            Ok(event) => event,
+            Err(_) => break,
+        };
+        if !is_relevant_event(&first, &outputs) {
+            continue;
+        }
+
+        // Debounce: keep draining until the folder is quiet for ~500ms.
+        loop {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(_) => continue,
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+
+        on_report(recombine());
+    }
+
+    Ok(())
+}
+
+fn is_relevant_event(res: &notify::Result<notify::Event>, outputs: &HashSet<&str>) -> bool {
+    use notify::EventKind;
+
+    let event = match res {
+        Ok(event) => event,
+        Err(_) => return false,
+    };
+
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return false;
+    }
+
+    event.paths.iter().any(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| !is_own_write(name, outputs))
+            .unwrap_or(true)
+    })
+}
+
+/// Whether a touched file is one the tool itself produced — a configured or
+/// built-in combined output, or a timestamped backup sidecar. Used to keep
+/// watch mode from re-triggering on its own writes.
+fn is_own_write(name: &str, outputs: &HashSet<&str>) -> bool {
+    outputs.contains(name) || is_backup_sidecar(name)
+}
+
+pub fn combine_group(file_type: FileType, files: &[PathBuf], output_path: &Path) -> GroupSummary {
+    combine_group_with_progress(file_type, files, output_path, &mut |_, _| {})
+}
+
+/// Combine a group honouring the requested [`MergeMode`].
+///
+/// `Concatenate` defers to [`combine_group_with_progress`]; `Chronological`
+/// buffers every data line, k-way merges them by their leading timestamp
+/// column, and drops exact duplicate rows.
+pub fn combine_group_with_mode<F>(
+    file_type: FileType,
+    files: &[PathBuf],
+    output_path: &Path,
+    mode: MergeMode,
+    options: &CombineOptions,
+    on_file_processed: &mut F,
+) -> GroupSummary
+where
+    F: FnMut(FileType, &PathBuf),
+{
+    match mode {
+        MergeMode::Concatenate => {
+            combine_group_with_progress(file_type, files, output_path, on_file_processed)
+        }
+        MergeMode::Chronological => {
+            combine_group_chronological(file_type, files, output_path, options, on_file_processed)
+        }
+    }
+}
+
+/// One data line awaiting the k-way merge: its parsed timestamp key (or, when
+/// the leading column doesn't parse, the previous line's key, or the file's
+/// first key for lines before any timestamp), and the raw bytes.
+struct TimedLine {
+    key: f64,
+    line: Vec<u8>,
+}
+
+/// Heap entry ordering merge candidates by timestamp, then by file and
+/// intra-file position so equal keys keep a stable, deterministic order.
+#[derive(PartialEq)]
+struct MergeHead {
+    key: f64,
+    file_index: usize,
+    line_index: usize,
+}
+
+impl Eq for MergeHead {}
+
+impl Ord for MergeHead {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key
+            .total_cmp(&other.key)
+            .then(self.file_index.cmp(&other.file_index))
+            .then(self.line_index.cmp(&other.line_index))
+    }
+}
+
+impl PartialOrd for MergeHead {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn combine_group_chronological<F>(
+    file_type: FileType,
+    files: &[PathBuf],
+    output_path: &Path,
+    options: &CombineOptions,
+    on_file_processed: &mut F,
+) -> GroupSummary
+where
+    F: FnMut(FileType, &PathBuf),
+{
+    let mut summary = GroupSummary {
+        group: group_label(file_type),
+        file_type,
+        input_files: files.len(),
+        output_path: None,
+        data_lines: 0,
+        duplicate_lines: 0,
+        backup_path: None,
+        header: None,
+        warnings: Vec::new(),
+        errors: Vec::new(),
+    };
+    combine_group_chronological_into(files, output_path, options, &mut summary, &mut |path| {
+        on_file_processed(file_type, path)
+    });
+    summary
+}
+
+/// Chronological counterpart to [`combine_group_into`]: parse each file,
+/// timestamp-order its lines, k-way merge across files, and drop duplicate
+/// rows. Honours `dry_run`/`backup` so the configured and Bead/Motor paths
+/// behave identically whichever merge mode is selected.
+fn combine_group_chronological_into(
+    files: &[PathBuf],
+    output_path: &Path,
+    options: &CombineOptions,
+    summary: &mut GroupSummary,
+    on_file: &mut dyn FnMut(&PathBuf),
+) {
+    if files.is_empty() {
+        return;
+    }
+
+    let mut header_ref: Option<Vec<u8>> = None;
+    let mut streams: Vec<Vec<TimedLine>> = Vec::with_capacity(files.len());
+    let mut saw_readable_file = false;
+
+    for path in files {
+        // Cooperative cancellation: stop before the next file and flush what we
+        // have already accumulated as a partial output.
+        if options.is_cancelled() {
+            break;
+        }
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                summary.errors.push(Error {
+                    file: Some(path.clone()),
+                    message: format!("Failed to read file: {err}"),
+                });
+                on_file(path);
+                continue;
+            }
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut buffer = Vec::new();
+        let mut file_header: Option<Vec<u8>> = None;
+        let mut stream: Vec<TimedLine> = Vec::new();
+        // `None` until the file's first parseable timestamp is seen; lines
+        // before it are recorded so they can inherit that first key rather than
+        // floating to the very top of the merge on a sentinel value.
+        let mut last_key: Option<f64> = None;
+        let mut leading_unkeyed: Vec<usize> = Vec::new();
+        let mut read_failed = false;
+
+        loop {
+            match read_next_line(&mut reader, &mut buffer) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    summary.errors.push(Error {
+                        file: Some(path.clone()),
+                        message: format!("Failed to read file: {err}"),
+                    });
+                    read_failed = true;
+                    break;
+                }
+            }
+
+            if is_whitespace_line(&buffer) {
+                continue;
+            }
+
+            if file_header.is_none() && starts_with_hash(&buffer) {
+                file_header = Some(buffer.clone());
+                if header_ref.is_none() {
+                    header_ref = file_header.clone();
+                    summary.header = file_header.clone();
+                } else if header_ref.as_deref() != file_header.as_deref() {
+                    summary.warnings.push(Warning {
+                        file: path.clone(),
+                        message: "Header mismatch".to_string(),
+                    });
+                }
+                continue;
+            }
+
+            match parse_leading_timestamp(&buffer) {
+                Some(key) => {
+                    // The first real key backfills any lines that preceded it so
+                    // they stay just ahead of it instead of at the global top.
+                    if last_key.is_none() {
+                        for index in leading_unkeyed.drain(..) {
+                            stream[index].key = key;
+                        }
+                    }
+                    last_key = Some(key);
+                    stream.push(TimedLine {
+                        key,
+                        line: buffer.clone(),
+                    });
+                }
+                None => {
+                    // Keep unparseable lines in their original position by
+                    // inheriting the previous timestamp, and flag them.
+                    summary.warnings.push(Warning {
+                        file: path.clone(),
+                        message: "Could not parse leading timestamp".to_string(),
+                    });
+                    if last_key.is_none() {
+                        leading_unkeyed.push(stream.len());
+                    }
+                    stream.push(TimedLine {
+                        key: last_key.unwrap_or(0.0),
+                        line: buffer.clone(),
+                    });
+                }
+            }
+        }
+
+        if read_failed {
+            on_file(path);
+            continue;
+        }
+
+        saw_readable_file = true;
+        streams.push(stream);
+        on_file(path);
+    }
+
+    if !saw_readable_file {
+        return;
+    }
+
+    // Stable k-way merge across the already-ordered per-file streams, dropping
+    // exact duplicate rows. Done before any write so dry-run can report the
+    // line count without touching disk.
+    let mut merged: Vec<&Vec<u8>> = Vec::new();
+    let mut heap: BinaryHeap<Reverse<MergeHead>> = BinaryHeap::new();
+    for (file_index, stream) in streams.iter().enumerate() {
+        if let Some(first) = stream.first() {
+            heap.push(Reverse(MergeHead {
+                key: first.key,
+                file_index,
+                line_index: 0,
+            }));
+        }
+    }
+
+    let mut seen: HashSet<u64> = HashSet::new();
+    while let Some(Reverse(head)) = heap.pop() {
+        let line = &streams[head.file_index][head.line_index];
+
+        let mut hasher = DefaultHasher::new();
+        line.line.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if seen.insert(hash) {
+            merged.push(&line.line);
+        } else {
+            summary.duplicate_lines += 1;
+        }
+
+        let next_index = head.line_index + 1;
+        if let Some(next) = streams[head.file_index].get(next_index) {
+            heap.push(Reverse(MergeHead {
+                key: next.key,
+                file_index: head.file_index,
+                line_index: next_index,
+            }));
+        }
+    }
+
+    summary.data_lines = merged.len();
+
+    // In dry-run mode we report the planned output but never touch the disk.
+    if options.dry_run {
+        summary.output_path = Some(output_path.to_path_buf());
+        return;
+    }
+
+    if options.backup && output_path.exists() {
+        match backup_path_for(output_path) {
+            Some(backup) => {
+                if let Err(err) = fs::rename(output_path, &backup) {
+                    summary.errors.push(Error {
+                        file: Some(output_path.to_path_buf()),
+                        message: format!("Failed to back up existing output: {err}"),
+                    });
+                    return;
+                }
+                summary.backup_path = Some(backup);
+            }
+            None => {
+                summary.errors.push(Error {
+                    file: Some(output_path.to_path_buf()),
+                    message: "Failed to derive backup path".to_string(),
+                });
+                return;
+            }
+        }
+    }
 
-    report.bead_files = discovered.bead_files.len();
-    report.motor_files = discovered.motor_files.len();
-    let total_files = report.bead_files + report.motor_files;
-    let mut processed_files = 0usize;
-    let mut on_file_processed = |file_type: FileType, path: &PathBuf| {
-        processed_files += 1;
-        on_progress(ProgressEvent::Combine {
-            processed_files,
-            total_files,
-            file_type,
-            current_file: path.clone(),
-        });
+    let mut writer_slot: Option<BufWriter<File>> = None;
+    let writer = match ensure_output_writer(&mut writer_slot, output_path, summary) {
+        Some(writer) => writer,
+        None => return,
     };
 
-    if !discovered.bead_files.is_empty() {
-        let output = folder.join(output_filename(FileType::Bead));
-        report.bead = Some(combine_group_with_progress(
-            FileType::Bead,
-            &discovered.bead_files,
-            &output,
-            &mut on_file_processed,
-        ));
+    if let Some(ref header) = header_ref {
+        if let Err(err) = write_line(writer, header) {
+            summary.errors.push(Error {
+                file: Some(output_path.to_path_buf()),
+                message: format!("Failed to write output: {err}"),
+            });
+            return;
+        }
     }
 
-    if !discovered.motor_files.is_empty() {
-        let output = folder.join(output_filename(FileType::Motor));
-        report.motor = Some(combine_group_with_progress(
-            FileType::Motor,
-            &discovered.motor_files,
-            &output,
-            &mut on_file_processed,
-        ));
+    for line in merged {
+        if let Err(err) = write_line(writer, line) {
+            summary.errors.push(Error {
+                file: Some(output_path.to_path_buf()),
+                message: format!("Failed to write output: {err}"),
+            });
+            return;
+        }
     }
-
-    report
 }
 
-pub fn combine_group(file_type: FileType, files: &[PathBuf], output_path: &Path) -> GroupSummary {
-    combine_group_with_progress(file_type, files, output_path, &mut |_, _| {})
+fn parse_leading_timestamp(line: &[u8]) -> Option<f64> {
+    let text = std::str::from_utf8(line).ok()?;
+    let token = text.split(|c: char| c == ' ' || c == '\t').find(|t| !t.is_empty())?;
+    token.parse::<f64>().ok()
 }
 
 pub fn combine_group_with_progress<F>(
@@ -207,10 +1309,13 @@ where
     F: FnMut(FileType, &PathBuf),
 {
     let mut summary = GroupSummary {
+        group: group_label(file_type),
         file_type,
         input_files: files.len(),
         output_path: None,
         data_lines: 0,
+        duplicate_lines: 0,
+        backup_path: None,
         header: None,
         warnings: Vec::new(),
         errors: Vec::new(),
@@ -353,6 +1458,269 @@ where
     summary
 }
 
+/// A single input file read and split in a worker thread.
+///
+/// `header` holds the first `#` line (if any); `lines` are the remaining
+/// non-blank lines in original order. Stitching happens on the caller's thread
+/// so the combined output matches the sequential path byte-for-byte.
+struct ParsedFile {
+    path: PathBuf,
+    header: Option<Vec<u8>>,
+    lines: Vec<Vec<u8>>,
+    error: Option<String>,
+}
+
+fn parse_group_file(path: &Path, options: &CombineOptions) -> ParsedFile {
+    let mut parsed = ParsedFile {
+        path: path.to_path_buf(),
+        header: None,
+        lines: Vec::new(),
+        error: None,
+    };
+
+    // Cooperative cancellation: a Stop requested while the pool is still
+    // reading short-circuits each remaining file instead of parsing it in full.
+    if options.is_cancelled() {
+        return parsed;
+    }
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            parsed.error = Some(format!("Failed to read file: {err}"));
+            return parsed;
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    loop {
+        // Also bail part-way through a single large file so a Stop doesn't block
+        // on the remainder of its lines.
+        if options.is_cancelled() {
+            break;
+        }
+        match read_next_line(&mut reader, &mut buffer) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => {
+                parsed.error = Some(format!("Failed to read file: {err}"));
+                return parsed;
+            }
+        }
+
+        if is_whitespace_line(&buffer) {
+            continue;
+        }
+
+        if parsed.header.is_none() && starts_with_hash(&buffer) {
+            parsed.header = Some(buffer.clone());
+            continue;
+        }
+
+        parsed.lines.push(buffer.clone());
+    }
+
+    parsed
+}
+
+fn combine_group_parallel<F>(
+    file_type: FileType,
+    files: &[PathBuf],
+    output_path: &Path,
+    options: &CombineOptions,
+    on_file_processed: &mut F,
+) -> GroupSummary
+where
+    F: FnMut(FileType, &PathBuf),
+{
+    let mut summary = GroupSummary {
+        group: group_label(file_type),
+        file_type,
+        input_files: files.len(),
+        output_path: None,
+        data_lines: 0,
+        duplicate_lines: 0,
+        backup_path: None,
+        header: None,
+        warnings: Vec::new(),
+        errors: Vec::new(),
+    };
+    match options.mode {
+        MergeMode::Concatenate => {
+            combine_group_into(files, output_path, options, &mut summary, &mut |path| {
+                on_file_processed(file_type, path)
+            });
+        }
+        MergeMode::Chronological => {
+            combine_group_chronological_into(files, output_path, options, &mut summary, &mut |path| {
+                on_file_processed(file_type, path)
+            });
+        }
+    }
+    summary
+}
+
+/// Parse `files` in parallel, stitch them into `output_path` in sorted order,
+/// and record the result on `summary`. Honours `dry_run`/`backup` and invokes
+/// `on_file` as each file is processed. Shared by the Bead/Motor and
+/// configured-group code paths.
+fn combine_group_into(
+    files: &[PathBuf],
+    output_path: &Path,
+    options: &CombineOptions,
+    summary: &mut GroupSummary,
+    on_file: &mut dyn FnMut(&PathBuf),
+) {
+    if files.is_empty() {
+        return;
+    }
+
+    // Read and parse every file in parallel; collect preserves input order.
+    // A configured thread count gets its own pool, otherwise the global one.
+    let read_all = || files.par_iter().map(|path| parse_group_file(path, options)).collect();
+    let parsed: Vec<ParsedFile> = match options.threads {
+        Some(threads) if threads > 0 => {
+            match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => pool.install(read_all),
+                // Fall back to the global pool if a dedicated one can't be built.
+                Err(_) => read_all(),
+            }
+        }
+        _ => read_all(),
+    };
+
+    // Stitch into the final ordered line list on this thread so the result is
+    // deterministic regardless of worker scheduling.
+    let mut header_ref: Option<Vec<u8>> = None;
+    let mut buffered_before_header: Vec<&Vec<u8>> = Vec::new();
+    let mut data: Vec<&Vec<u8>> = Vec::new();
+    let mut saw_readable_file = false;
+
+    for file in &parsed {
+        // Cooperative cancellation: stop before the next file and flush what we
+        // have already accumulated as a partial output.
+        if options.is_cancelled() {
+            break;
+        }
+
+        let path = &file.path;
+
+        if let Some(message) = &file.error {
+            summary.errors.push(Error {
+                file: Some(path.clone()),
+                message: message.clone(),
+            });
+            on_file(path);
+            continue;
+        }
+
+        if let Some(file_header) = &file.header {
+            if header_ref.is_none() {
+                header_ref = Some(file_header.clone());
+                summary.header = Some(file_header.clone());
+                data.append(&mut buffered_before_header);
+            } else if header_ref.as_deref() != Some(file_header.as_slice()) {
+                summary.warnings.push(Warning {
+                    file: path.clone(),
+                    message: "Header mismatch".to_string(),
+                });
+            }
+            data.extend(file.lines.iter());
+        } else if header_ref.is_none() {
+            buffered_before_header.extend(file.lines.iter());
+        } else {
+            data.extend(file.lines.iter());
+        }
+
+        saw_readable_file = true;
+        on_file(path);
+    }
+
+    if header_ref.is_none() {
+        data.append(&mut buffered_before_header);
+    }
+
+    summary.data_lines = data.len();
+
+    if !saw_readable_file {
+        return;
+    }
+
+    // In dry-run mode we report the planned output but never touch the disk.
+    if options.dry_run {
+        summary.output_path = Some(output_path.to_path_buf());
+        return;
+    }
+
+    if options.backup && output_path.exists() {
+        match backup_path_for(output_path) {
+            Some(backup) => {
+                if let Err(err) = fs::rename(output_path, &backup) {
+                    summary.errors.push(Error {
+                        file: Some(output_path.to_path_buf()),
+                        message: format!("Failed to back up existing output: {err}"),
+                    });
+                    return;
+                }
+                summary.backup_path = Some(backup);
+            }
+            None => {
+                summary.errors.push(Error {
+                    file: Some(output_path.to_path_buf()),
+                    message: "Failed to derive backup path".to_string(),
+                });
+                return;
+            }
+        }
+    }
+
+    let mut writer_slot: Option<BufWriter<File>> = None;
+    let writer = match ensure_output_writer(&mut writer_slot, output_path, summary) {
+        Some(writer) => writer,
+        None => return,
+    };
+
+    if let Some(ref header) = header_ref {
+        if let Err(err) = write_line(writer, header) {
+            summary.errors.push(Error {
+                file: Some(output_path.to_path_buf()),
+                message: format!("Failed to write output: {err}"),
+            });
+            return;
+        }
+    }
+
+    for line in data {
+        if let Err(err) = write_line(writer, line) {
+            summary.errors.push(Error {
+                file: Some(output_path.to_path_buf()),
+                message: format!("Failed to write output: {err}"),
+            });
+            return;
+        }
+    }
+}
+
+/// Build a timestamped sidecar path next to `output_path`, e.g.
+/// `Bead Positions Combined.bak.<unix_secs>.txt`.
+fn backup_path_for(output_path: &Path) -> Option<PathBuf> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let stem = output_path.file_stem().and_then(|s| s.to_str())?;
+    let ext = output_path.extension().and_then(|e| e.to_str());
+    let file_name = match ext {
+        Some(ext) => format!("{stem}.bak.{secs}.{ext}"),
+        None => format!("{stem}.bak.{secs}"),
+    };
+    Some(output_path.with_file_name(file_name))
+}
+
 pub fn output_filename(file_type: FileType) -> &'static str {
     match file_type {
         FileType::Bead => BEAD_OUTPUT,
@@ -375,24 +1743,16 @@ pub fn format_group_output(summary: Option<&GroupSummary>, label: &str) -> Strin
 }
 
 pub fn collect_warnings(report: &CombineReport) -> Vec<Warning> {
-    let mut warnings = Vec::new();
-    if let Some(ref bead) = report.bead {
-        warnings.extend(bead.warnings.clone());
-    }
-    if let Some(ref motor) = report.motor {
-        warnings.extend(motor.warnings.clone());
-    }
-    warnings
+    report
+        .groups
+        .iter()
+        .flat_map(|group| group.warnings.iter().cloned())
+        .collect()
 }
 
 pub fn collect_errors(report: &CombineReport) -> Vec<Error> {
     let mut errors = report.errors.clone();
-    if let Some(ref bead) = report.bead {
-        errors.extend(bead.errors.clone());
-    }
-    if let Some(ref motor) = report.motor {
-        errors.extend(motor.errors.clone());
-    }
+    errors.extend(report.groups.iter().flat_map(|group| group.errors.iter().cloned()));
     errors
 }
 
@@ -410,6 +1770,20 @@ fn is_combined_output(name: &str) -> bool {
     name == BEAD_OUTPUT || name == MOTOR_OUTPUT
 }
 
+/// Whether `name` is a timestamped backup sidecar produced by
+/// [`backup_path_for`], i.e. `<stem>.bak.<unix_secs>[.<ext>]`. Such files share
+/// a combined output's prefix and `.txt` extension, so discovery must skip them
+/// or a second `--backup` run would merge old backups back into the output.
+fn is_backup_sidecar(name: &str) -> bool {
+    match name.rfind(".bak.") {
+        Some(idx) => {
+            let secs = name[idx + ".bak.".len()..].split('.').next().unwrap_or("");
+            !secs.is_empty() && secs.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
 fn sort_paths(paths: &mut Vec<PathBuf>) {
     paths.sort_by(|a, b| file_name_key(a).cmp(file_name_key(b)));
 }
@@ -571,6 +1945,250 @@ mod tests {
             .any(|w| w.message.contains("Header mismatch")));
     }
 
+    #[test]
+    fn combine_group_chronological_merges_and_dedupes() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\ta\n3\tc\n");
+        write_file(&dir.path().join("Bead Positions 2.txt"), "# H\n2\tb\n3\tc\n");
+
+        let files = vec![
+            dir.path().join("Bead Positions 1.txt"),
+            dir.path().join("Bead Positions 2.txt"),
+        ];
+        let output = dir.path().join("Bead Positions Combined.txt");
+        let summary = combine_group_with_mode(
+            FileType::Bead,
+            &files,
+            &output,
+            MergeMode::Chronological,
+            &CombineOptions::default(),
+            &mut |_, _| {},
+        );
+
+        let content = fs::read_to_string(&output).expect("read output");
+        assert_eq!(content, "# H\n1\ta\n2\tb\n3\tc\n");
+        assert_eq!(summary.data_lines, 3);
+        assert_eq!(summary.duplicate_lines, 1);
+    }
+
+    #[test]
+    fn combine_group_chronological_keeps_leading_unparseable_in_place() {
+        let dir = tempdir().expect("tempdir");
+        // A junk line precedes any timestamp in file 1; it must inherit the
+        // first key (5) and not jump ahead of file 2's earlier row.
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\njunk\n5\tx\n");
+        write_file(&dir.path().join("Bead Positions 2.txt"), "# H\n1\ta\n");
+
+        let files = vec![
+            dir.path().join("Bead Positions 1.txt"),
+            dir.path().join("Bead Positions 2.txt"),
+        ];
+        let output = dir.path().join("Bead Positions Combined.txt");
+        let summary = combine_group_with_mode(
+            FileType::Bead,
+            &files,
+            &output,
+            MergeMode::Chronological,
+            &CombineOptions::default(),
+            &mut |_, _| {},
+        );
+
+        let content = fs::read_to_string(&output).expect("read output");
+        assert_eq!(content, "# H\n1\ta\njunk\n5\tx\n");
+        assert_eq!(summary.data_lines, 3);
+    }
+
+    #[test]
+    fn combine_folder_chronological_mode_merges_by_timestamp() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\ta\n3\tc\n");
+        write_file(&dir.path().join("Bead Positions 2.txt"), "# H\n2\tb\n3\tc\n");
+
+        let options = CombineOptions {
+            mode: MergeMode::Chronological,
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        let bead = report.bead.expect("bead summary");
+        assert_eq!(bead.data_lines, 3);
+        assert_eq!(bead.duplicate_lines, 1);
+
+        let content = fs::read_to_string(dir.path().join("Bead Positions Combined.txt"))
+            .expect("read output");
+        assert_eq!(content, "# H\n1\ta\n2\tb\n3\tc\n");
+    }
+
+    #[test]
+    fn combine_folder_with_options_walks_subfolders() {
+        let dir = tempdir().expect("tempdir");
+        let sub = dir.path().join("session_1");
+        fs::create_dir(&sub).expect("create subdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n");
+        write_file(&sub.join("Bead Positions 2.txt"), "# H\n2\n");
+
+        let options = CombineOptions {
+            recursive: true,
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        let bead = report.bead.expect("bead summary");
+        assert_eq!(report.bead_files, 2);
+        assert_eq!(bead.data_lines, 2);
+
+        let output = dir.path().join("Bead Positions Combined.txt");
+        let content = fs::read_to_string(&output).expect("read output");
+        assert!(content.starts_with("# H\n"));
+        assert!(content.contains("1\n"));
+        assert!(content.contains("2\n"));
+    }
+
+    #[test]
+    fn combine_folder_respects_max_depth() {
+        let dir = tempdir().expect("tempdir");
+        let sub = dir.path().join("session_1");
+        fs::create_dir(&sub).expect("create subdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n");
+        write_file(&sub.join("Bead Positions 2.txt"), "# H\n2\n");
+
+        let options = CombineOptions {
+            recursive: true,
+            max_depth: Some(0),
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        // Depth 0 is the target folder only, so the nested file is not picked up.
+        assert_eq!(report.bead_files, 1);
+    }
+
+    #[test]
+    fn combine_folder_dry_run_reports_without_writing() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n2\n");
+
+        let options = CombineOptions {
+            dry_run: true,
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        let bead = report.bead.expect("bead summary");
+        assert_eq!(bead.data_lines, 2);
+        assert!(bead.output_path.is_some());
+        assert!(!dir.path().join("Bead Positions Combined.txt").exists());
+    }
+
+    #[test]
+    fn combine_folder_backs_up_existing_output() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n");
+        write_file(&dir.path().join("Bead Positions Combined.txt"), "# OLD\nold\n");
+
+        let options = CombineOptions {
+            backup: true,
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        let bead = report.bead.expect("bead summary");
+        let backup = bead.backup_path.expect("backup path");
+        assert!(backup.exists());
+        assert_eq!(fs::read_to_string(&backup).expect("read backup"), "# OLD\nold\n");
+    }
+
+    #[test]
+    fn backup_sidecars_are_not_reingested_on_rerun() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n");
+
+        let options = CombineOptions {
+            backup: true,
+            ..CombineOptions::default()
+        };
+
+        // Leave a sidecar behind as a prior run would.
+        write_file(
+            &dir.path().join("Bead Positions Combined.bak.1700000000.txt"),
+            "# H\nstale\n",
+        );
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        // The sidecar must not be classified as a Bead input.
+        assert_eq!(report.bead_files, 1);
+        assert_eq!(report.bead.expect("bead summary").data_lines, 1);
+    }
+
+    #[test]
+    fn combine_folder_with_config_uses_custom_groups() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Force 1.txt"), "# H\n1\n");
+        write_file(&dir.path().join("Force 2.txt"), "# H\n2\n");
+        // Skipped by the group's extension allowlist.
+        write_file(&dir.path().join("Force 3.bak"), "# H\n9\n");
+
+        let config = ClassifyConfig {
+            groups: vec![GroupDef {
+                label: "Force".to_string(),
+                prefix: Some("Force".to_string()),
+                suffix: None,
+                regex: None,
+                extensions: vec!["txt".to_string()],
+                output: "Force Combined.txt".to_string(),
+            }],
+            excludes: Vec::new(),
+        };
+
+        let report = combine_folder_with_config(
+            dir.path(),
+            &config,
+            &CombineOptions::default(),
+            |_| {},
+        );
+        let force = report.groups.first().expect("force summary");
+        assert_eq!(force.group, "Force");
+        assert_eq!(force.input_files, 2);
+        assert_eq!(force.data_lines, 2);
+
+        let content = fs::read_to_string(dir.path().join("Force Combined.txt")).expect("read output");
+        assert!(content.starts_with("# H\n"));
+    }
+
+    #[test]
+    fn combine_folder_is_deterministic_across_thread_counts() {
+        let dir = tempdir().expect("tempdir");
+        for i in 1..=5 {
+            write_file(
+                &dir.path().join(format!("Bead Positions {i}.txt")),
+                &format!("# H\n{i}\n"),
+            );
+        }
+
+        let options = CombineOptions {
+            threads: Some(4),
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        assert_eq!(report.bead.expect("bead summary").data_lines, 5);
+
+        let content = fs::read_to_string(dir.path().join("Bead Positions Combined.txt"))
+            .expect("read output");
+        assert_eq!(content, "# H\n1\n2\n3\n4\n5\n");
+    }
+
+    #[test]
+    fn combine_folder_stops_when_cancelled() {
+        let dir = tempdir().expect("tempdir");
+        write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n");
+
+        let stop = Arc::new(AtomicBool::new(true));
+        let options = CombineOptions {
+            stop: Some(stop),
+            ..CombineOptions::default()
+        };
+        let report = combine_folder_with_options(dir.path(), &options, |_| {});
+        assert!(report.cancelled);
+        // No file was processed before the cancellation check fired.
+        let bead = report.bead.expect("bead summary");
+        assert_eq!(bead.data_lines, 0);
+        assert!(!dir.path().join("Bead Positions Combined.txt").exists());
+    }
+
     #[test]
     fn combine_folder_handles_empty_groups() {
         let dir = tempdir().expect("tempdir");
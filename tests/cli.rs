@@ -35,6 +35,27 @@ fn cli_combines_files() {
     assert!(stdout.contains("Motor files: 1"));
 }
 
+#[test]
+fn cli_format_json_emits_report() {
+    let dir = tempdir().expect("tempdir");
+    write_file(&dir.path().join("Bead Positions 1.txt"), "# H\n1\n2\n");
+
+    let args = vec![
+        "magscope_cli".to_string(),
+        "--format".to_string(),
+        "json".to_string(),
+        dir.path().to_string_lossy().to_string(),
+    ];
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let exit_code = magscope_file_combiner::cli::run_cli(&args, &mut stdout, &mut stderr);
+    assert_eq!(exit_code, 0);
+
+    let stdout = String::from_utf8_lossy(&stdout);
+    assert!(stdout.contains("\"bead_files\": 1"));
+    assert!(stdout.contains("\"data_lines\": 2"));
+}
+
 #[test]
 fn cli_reports_no_matching_files() {
     let dir = tempdir().expect("tempdir");